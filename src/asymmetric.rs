@@ -0,0 +1,138 @@
+// Copyright 2020 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+//! Asymmetric signature and encryption operations forwarded to the Parsec client.
+use crate::{client_error_to_psa_status, key_slot_to_key_name, PARSEC_BASIC_CLIENT};
+use psa_crypto::ffi::{
+    psa_algorithm_t, psa_drv_se_asymmetric_t, psa_drv_se_context_t, psa_key_slot_number_t, psa_status_t,
+    PSA_ERROR_BUFFER_TOO_SMALL, PSA_SUCCESS,
+};
+use std::{ptr, slice};
+
+/// Methods table exposed as `PARSEC_SE_DRIVER.asymmetric`.
+pub static METHODS: psa_drv_se_asymmetric_t = psa_drv_se_asymmetric_t {
+    p_sign: Some(p_sign),
+    p_verify: Some(p_verify),
+    p_encrypt: Some(p_asym_encrypt),
+    p_decrypt: Some(p_asym_decrypt),
+};
+
+unsafe extern "C" fn p_sign(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    alg: psa_algorithm_t,
+    p_hash: *const u8,
+    hash_length: usize,
+    p_signature: *mut u8,
+    signature_size: usize,
+    p_signature_length: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let hash = slice::from_raw_parts(p_hash, hash_length);
+
+    match client.psa_sign_hash(key_name, alg, hash) {
+        Ok(signature) => {
+            if signature.len() > signature_size {
+                return PSA_ERROR_BUFFER_TOO_SMALL;
+            }
+            ptr::copy_nonoverlapping(signature.as_ptr(), p_signature, signature.len());
+            *p_signature_length = signature.len();
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+unsafe extern "C" fn p_verify(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    alg: psa_algorithm_t,
+    p_hash: *const u8,
+    hash_length: usize,
+    p_signature: *const u8,
+    signature_length: usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let hash = slice::from_raw_parts(p_hash, hash_length);
+    let signature = slice::from_raw_parts(p_signature, signature_length);
+
+    match client.psa_verify_hash(key_name, alg, hash, signature) {
+        Ok(()) => PSA_SUCCESS,
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+/// Encrypt a buffer with an asymmetric key living behind the SE driver (e.g. RSA-OAEP,
+/// RSA-PKCS1v15), forwarding to the Parsec client's `psa_asymmetric_encrypt`.
+unsafe extern "C" fn p_asym_encrypt(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    alg: psa_algorithm_t,
+    p_input: *const u8,
+    input_length: usize,
+    p_salt: *const u8,
+    salt_length: usize,
+    p_output: *mut u8,
+    output_size: usize,
+    p_output_length: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let input = slice::from_raw_parts(p_input, input_length);
+    let salt = asym_salt(p_salt, salt_length);
+
+    match client.psa_asymmetric_encrypt(key_name, alg, input, salt) {
+        Ok(buffer) => {
+            if buffer.len() > output_size {
+                return PSA_ERROR_BUFFER_TOO_SMALL;
+            }
+            ptr::copy_nonoverlapping(buffer.as_ptr(), p_output, buffer.len());
+            *p_output_length = buffer.len();
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+/// Decrypt a buffer with an asymmetric key living behind the SE driver, forwarding to the
+/// Parsec client's `psa_asymmetric_decrypt`.
+unsafe extern "C" fn p_asym_decrypt(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    alg: psa_algorithm_t,
+    p_input: *const u8,
+    input_length: usize,
+    p_salt: *const u8,
+    salt_length: usize,
+    p_output: *mut u8,
+    output_size: usize,
+    p_output_length: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let input = slice::from_raw_parts(p_input, input_length);
+    let salt = asym_salt(p_salt, salt_length);
+
+    match client.psa_asymmetric_decrypt(key_name, alg, input, salt) {
+        Ok(buffer) => {
+            if buffer.len() > output_size {
+                return PSA_ERROR_BUFFER_TOO_SMALL;
+            }
+            ptr::copy_nonoverlapping(buffer.as_ptr(), p_output, buffer.len());
+            *p_output_length = buffer.len();
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+/// Translate the optional salt/label buffer passed over FFI into the `Option<Vec<u8>>` the
+/// Parsec client expects, treating an empty buffer the same as no salt at all.
+unsafe fn asym_salt(p_salt: *const u8, salt_length: usize) -> Option<Vec<u8>> {
+    if salt_length == 0 {
+        None
+    } else {
+        Some(slice::from_raw_parts(p_salt, salt_length).to_vec())
+    }
+}
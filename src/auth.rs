@@ -0,0 +1,65 @@
+// Copyright 2020 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+//! Runtime selection of the authentication method presented to the Parsec service.
+use parsec_client::auth::AuthenticationData;
+use secrecy::SecretString;
+use std::env;
+
+/// Environment variable used to pick the authenticator, instead of always hardcoding
+/// direct/app-identity authentication.
+const AUTH_METHOD_ENV_VAR: &str = "PARSEC_SE_DRIVER_AUTH_METHOD";
+
+/// Environment variable carrying the application name/identity string presented to the
+/// authenticators that need one.
+const APP_NAME_ENV_VAR: &str = "PARSEC_SE_DRIVER_APP_NAME";
+
+/// Identity used when `PARSEC_SE_DRIVER_APP_NAME` is unset.
+const DEFAULT_APP_NAME: &str = "Parsec SE Driver";
+
+/// The outcome of reading `PARSEC_SE_DRIVER_AUTH_METHOD`, mirroring
+/// `provider::ProviderSelection`: distinguishing "unset" (fall back to direct/app-identity
+/// authentication) from "set to a name we don't recognize" (a misconfiguration that should fail
+/// loudly rather than silently fall back to the same thing).
+pub enum AuthSelection {
+    /// The variable wasn't set.
+    Unset,
+    /// The variable named an authenticator we know how to build.
+    Known(AuthenticationData),
+    /// The variable was set, but didn't match any known authenticator name.
+    Unknown(String),
+}
+
+/// The identity string presented to authenticators that need one, read from
+/// `PARSEC_SE_DRIVER_APP_NAME` and kept in a `SecretString` so it isn't left in plaintext in
+/// memory once handed to the client.
+fn configured_app_name() -> SecretString {
+    SecretString::new(env::var(APP_NAME_ENV_VAR).unwrap_or_else(|_| DEFAULT_APP_NAME.into()))
+}
+
+fn app_identity() -> AuthenticationData {
+    AuthenticationData::AppIdentity(configured_app_name())
+}
+
+/// Read the authenticator to use from `PARSEC_SE_DRIVER_AUTH_METHOD`, matched
+/// case-insensitively among `direct`, `unix-peer-credentials` and `jwt-svid`.
+pub fn configured_auth() -> AuthSelection {
+    let name = match env::var(AUTH_METHOD_ENV_VAR) {
+        Ok(name) => name,
+        Err(_) => return AuthSelection::Unset,
+    };
+
+    if name.eq_ignore_ascii_case("direct") {
+        AuthSelection::Known(app_identity())
+    } else if name.eq_ignore_ascii_case("unix-peer-credentials") {
+        AuthSelection::Known(AuthenticationData::UnixPeerCredentials)
+    } else if name.eq_ignore_ascii_case("jwt-svid") {
+        AuthSelection::Known(AuthenticationData::JwtSvid(configured_app_name()))
+    } else {
+        AuthSelection::Unknown(name)
+    }
+}
+
+/// The `AuthenticationData` to use when `PARSEC_SE_DRIVER_AUTH_METHOD` is unset.
+pub fn default_auth() -> AuthenticationData {
+    app_identity()
+}
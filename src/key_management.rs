@@ -0,0 +1,195 @@
+// Copyright 2020 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+//! Key management operations forwarded to the Parsec client.
+use crate::{
+    client_error_to_psa_status, default_key_name, key_slot_to_key_name, persistent,
+    PARSEC_BASIC_CLIENT, SLOT_TABLE,
+};
+use psa_crypto::ffi::{
+    psa_drv_se_context_t, psa_drv_se_key_management_t, psa_key_attributes_t,
+    psa_key_creation_method_t, psa_key_slot_number_t, psa_status_t, PSA_ERROR_BUFFER_TOO_SMALL,
+    PSA_ERROR_INSUFFICIENT_STORAGE, PSA_SUCCESS,
+};
+use std::collections::HashMap;
+use std::{ptr, slice};
+
+/// Methods table exposed as `PARSEC_SE_DRIVER.key_management`.
+pub static METHODS: psa_drv_se_key_management_t = psa_drv_se_key_management_t {
+    p_allocate: Some(p_allocate),
+    p_validate_slot_number: Some(p_validate_slot_number),
+    p_import: Some(p_import),
+    p_generate: Some(p_generate),
+    p_destroy: Some(p_destroy),
+    p_export: None,
+    p_export_public: Some(p_export_public),
+};
+
+unsafe extern "C" fn p_allocate(
+    _drv_context: *mut psa_drv_se_context_t,
+    persistent_data: *mut ::std::os::raw::c_void,
+    _attributes: *const psa_key_attributes_t,
+    _method: psa_key_creation_method_t,
+    key_slot: *mut psa_key_slot_number_t,
+) -> psa_status_t {
+    let mut table = SLOT_TABLE.write().expect("lock poisoned");
+
+    let slot = match next_free_slot(&table) {
+        Some(slot) => slot,
+        None => return PSA_ERROR_INSUFFICIENT_STORAGE,
+    };
+    let key_name = default_key_name(slot);
+
+    let _ = table.insert(slot, key_name);
+    persistent::write_table(persistent_data, &table);
+
+    *key_slot = slot;
+    PSA_SUCCESS
+}
+
+/// Pick the lowest slot number not already tracked in `table` (restored from
+/// `persistent_data`), rather than a process-lifetime counter: a counter reset by a restart
+/// would hand out a slot that's already mapped to a live, persisted key name. Returns `None`
+/// once `persistent::MAX_ENTRIES` tracked slots are reached, rather than letting the table grow
+/// past what `persistent::write_table` can serialize.
+///
+/// Factored out of `p_allocate` so the allocation policy can be unit tested without going
+/// through the FFI surface.
+fn next_free_slot(table: &HashMap<psa_key_slot_number_t, String>) -> Option<psa_key_slot_number_t> {
+    if table.len() >= persistent::MAX_ENTRIES {
+        return None;
+    }
+    (1..).find(|candidate| !table.contains_key(candidate))
+}
+
+unsafe extern "C" fn p_validate_slot_number(
+    _drv_context: *mut psa_drv_se_context_t,
+    _persistent_data: *mut ::std::os::raw::c_void,
+    _attributes: *const psa_key_attributes_t,
+    _method: psa_key_creation_method_t,
+    _key_slot: psa_key_slot_number_t,
+) -> psa_status_t {
+    PSA_SUCCESS
+}
+
+unsafe extern "C" fn p_generate(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    attributes: *const psa_key_attributes_t,
+    p_pubkey: *mut u8,
+    pubkey_size: usize,
+    p_pubkey_length: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+
+    match client.psa_generate_key(key_name, *attributes) {
+        Ok(()) => {
+            *p_pubkey_length = 0;
+            let _ = pubkey_size;
+            let _ = p_pubkey;
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+/// Import externally-created key material into the SE driver's slot, forwarding to the
+/// Parsec client's `psa_import_key`. Used by backends with fixed physical key slots (e.g. the
+/// CryptoAuthLib provider fronting an ATECC508A/608A) that support importing into a given slot.
+unsafe extern "C" fn p_import(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    attributes: *const psa_key_attributes_t,
+    p_data: *const u8,
+    data_length: usize,
+    p_bits: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let data = slice::from_raw_parts(p_data, data_length);
+
+    match client.psa_import_key(key_name, *attributes, data) {
+        Ok(bits) => {
+            *p_bits = bits;
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+unsafe extern "C" fn p_destroy(
+    _drv_context: *mut psa_drv_se_context_t,
+    persistent_data: *mut ::std::os::raw::c_void,
+    key_slot: psa_key_slot_number_t,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+
+    match client.psa_destroy_key(key_name) {
+        Ok(()) => {
+            let mut table = SLOT_TABLE.write().expect("lock poisoned");
+            let _ = table.remove(&key_slot);
+            persistent::write_table(persistent_data, &table);
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+unsafe extern "C" fn p_export_public(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    p_data: *mut u8,
+    data_size: usize,
+    p_data_length: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+
+    match client.psa_export_public_key(key_name) {
+        Ok(buffer) => {
+            if buffer.len() > data_size {
+                return PSA_ERROR_BUFFER_TOO_SMALL;
+            }
+            ptr::copy_nonoverlapping(buffer.as_ptr(), p_data, buffer.len());
+            *p_data_length = buffer.len();
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_free_slot;
+    use std::collections::HashMap;
+
+    #[test]
+    fn picks_lowest_free_slot() {
+        let mut table = HashMap::new();
+        let _ = table.insert(1, "parsec-se-driver-key1".to_string());
+        let _ = table.insert(3, "parsec-se-driver-key3".to_string());
+
+        assert_eq!(next_free_slot(&table), Some(2));
+    }
+
+    #[test]
+    fn reuses_slot_after_destroy() {
+        let mut table = HashMap::new();
+        let _ = table.insert(1, "parsec-se-driver-key1".to_string());
+        let _ = table.insert(2, "parsec-se-driver-key2".to_string());
+
+        let _ = table.remove(&1);
+
+        assert_eq!(next_free_slot(&table), Some(1));
+    }
+
+    #[test]
+    fn rejects_allocation_past_capacity() {
+        let table: HashMap<_, _> = (0..super::persistent::MAX_ENTRIES as u64)
+            .map(|slot| (slot + 1, format!("parsec-se-driver-key{}", slot + 1)))
+            .collect();
+
+        assert_eq!(next_free_slot(&table), None);
+    }
+}
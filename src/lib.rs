@@ -34,24 +34,28 @@
 #![allow(clippy::multiple_crate_versions)]
 
 mod asymmetric;
+mod auth;
 mod key_management;
+mod mac;
+mod persistent;
+mod provider;
 
 use psa_crypto::ffi::{
-    psa_drv_se_asymmetric_t, psa_drv_se_context_t, psa_drv_se_key_management_t, psa_drv_se_t,
-    psa_key_lifetime_t, psa_key_slot_number_t, psa_status_t,
+    psa_drv_se_asymmetric_t, psa_drv_se_context_t, psa_drv_se_key_management_t, psa_drv_se_mac_t,
+    psa_drv_se_t, psa_key_lifetime_t, psa_key_slot_number_t, psa_status_t,
 };
 use psa_crypto::ffi::{
     PSA_ERROR_ALREADY_EXISTS,
     PSA_ERROR_BAD_STATE,
     PSA_ERROR_BUFFER_TOO_SMALL,
     PSA_ERROR_COMMUNICATION_FAILURE,
+    PSA_ERROR_CORRUPTION_DETECTED,
+    PSA_ERROR_DATA_CORRUPT,
+    PSA_ERROR_DATA_INVALID,
     PSA_ERROR_DOES_NOT_EXIST,
     PSA_ERROR_GENERIC_ERROR,
-    //PSA_ERROR_DATA_CORRUPT,
-    //PSA_ERROR_DATA_INVALID,
     PSA_ERROR_HARDWARE_FAILURE,
     PSA_ERROR_INSUFFICIENT_DATA,
-    //PSA_ERROR_CORRUPTION_DETECTED,
     PSA_ERROR_INSUFFICIENT_ENTROPY,
     PSA_ERROR_INSUFFICIENT_MEMORY,
     PSA_ERROR_INSUFFICIENT_STORAGE,
@@ -67,26 +71,30 @@ use psa_crypto::ffi::{
 
 use lazy_static::lazy_static;
 use log::error;
-use parsec_client::core::interface::operations::list_providers::Uuid;
 use parsec_client::core::interface::requests::ResponseStatus;
-use parsec_client::error::Error;
+use parsec_client::error::{ClientErrorKind, Error};
 use parsec_client::BasicClient;
+use std::collections::HashMap;
 use std::ptr;
 use std::sync::RwLock;
 use std::time::Duration;
 
 lazy_static! {
     static ref PARSEC_BASIC_CLIENT: RwLock<BasicClient> = RwLock::new(BasicClient::new_naked());
+    /// Slot-to-key-name table, kept in memory for lookups and mirrored into the PSA-provided
+    /// `persistent_data` blob by the key management callbacks.
+    static ref SLOT_TABLE: RwLock<HashMap<psa_key_slot_number_t, String>> =
+        RwLock::new(HashMap::new());
 }
 
-/// SE Driver implementation which hardcodes the authentication method (direct authentication).
+/// SE Driver implementation backed by a Parsec client.
 #[no_mangle]
 pub static mut PARSEC_SE_DRIVER: psa_drv_se_t = psa_drv_se_t {
     hal_version: 5,
-    persistent_data_size: 0,
+    persistent_data_size: persistent::TABLE_SIZE,
     p_init: Some(p_init),
     key_management: &key_management::METHODS as *const psa_drv_se_key_management_t,
-    mac: ptr::null(),
+    mac: &mac::METHODS as *const psa_drv_se_mac_t,
     cipher: ptr::null(),
     aead: ptr::null(),
     asymmetric: &asymmetric::METHODS as *const psa_drv_se_asymmetric_t,
@@ -95,11 +103,13 @@ pub static mut PARSEC_SE_DRIVER: psa_drv_se_t = psa_drv_se_t {
 
 unsafe extern "C" fn p_init(
     _drv_context: *mut psa_drv_se_context_t,
-    _persistent_data: *mut ::std::os::raw::c_void,
+    persistent_data: *mut ::std::os::raw::c_void,
     _location: psa_key_lifetime_t,
 ) -> psa_status_t {
     let mut client = (*PARSEC_BASIC_CLIENT).write().expect("lock poisoned");
 
+    *(SLOT_TABLE.write().expect("lock poisoned")) = persistent::read_table(persistent_data);
+
     #[cfg(feature = "logging")]
     // Ignore if the initialisation failed because the `p_init` function has already been called.
     let _ = env_logger::try_init();
@@ -108,7 +118,19 @@ unsafe extern "C" fn p_init(
 
     client.set_timeout(Some(Duration::new(5, 0)));
 
-    if let Err(e) = client.set_default_auth(Some(String::from("Parsec SE Driver"))) {
+    let auth_data = match auth::configured_auth() {
+        auth::AuthSelection::Known(data) => data,
+        auth::AuthSelection::Unset => auth::default_auth(),
+        auth::AuthSelection::Unknown(name) => {
+            error!(
+                "PARSEC_SE_DRIVER_AUTH_METHOD is set to \"{}\", which isn't a known auth method name.",
+                name
+            );
+            return PSA_ERROR_GENERIC_ERROR;
+        }
+    };
+
+    if let Err(e) = client.set_default_auth(Some(auth_data)) {
         error!("Error setting the default authentication method ({}).", e);
         return PSA_ERROR_GENERIC_ERROR;
     }
@@ -120,17 +142,22 @@ unsafe extern "C" fn p_init(
             return PSA_ERROR_GENERIC_ERROR;
         }
     };
-    let provider_id = match providers.iter().find(|p| {
-        if cfg!(feature = "tpm") {
-            // Only keep the TPM provider.
-            p.uuid == Uuid::parse_str("1e4954a4-ff21-46d3-ab0c-661eeb667e1d").unwrap()
-        } else if cfg!(feature = "pkcs11") {
-            // Only keep the PKCS11 provider.
-            p.uuid == Uuid::parse_str("30e39502-eba6-4d60-a4af-c518b7f5e38f").unwrap()
-        } else {
-            // Filter the Core provider.
-            p.uuid != Uuid::parse_str("47049873-2a43-4845-9d72-831eab668784").unwrap()
+    let provider_uuid = match provider::configured_provider() {
+        provider::ProviderSelection::Known(uuid) => Some(uuid),
+        provider::ProviderSelection::Unset => None,
+        provider::ProviderSelection::Unknown(name) => {
+            error!(
+                "PARSEC_SE_DRIVER_PROVIDER is set to \"{}\", which isn't a known provider name.",
+                name
+            );
+            return PSA_ERROR_GENERIC_ERROR;
         }
+    };
+    let provider_id = match providers.iter().find(|p| match provider_uuid {
+        // A backend was pinned via `PARSEC_SE_DRIVER_PROVIDER`: only keep that provider.
+        Some(uuid) => p.uuid == uuid,
+        // No explicit choice: keep the first provider that isn't the Core provider.
+        None => p.uuid != provider::core_provider_uuid(),
     }) {
         Some(provider) => provider.id,
         None => {
@@ -145,6 +172,17 @@ unsafe extern "C" fn p_init(
 }
 
 fn key_slot_to_key_name(key_slot: psa_key_slot_number_t) -> String {
+    if let Some(name) = SLOT_TABLE.read().expect("lock poisoned").get(&key_slot) {
+        return name.clone();
+    }
+    default_key_name(key_slot)
+}
+
+/// The deterministic name generated for a slot that has no persisted mapping yet. Kept
+/// separate from `key_slot_to_key_name` so callers already holding the `SLOT_TABLE` lock
+/// (e.g. `key_management::p_allocate`, picking a fresh slot) can derive a name without trying
+/// to re-acquire it.
+fn default_key_name(key_slot: psa_key_slot_number_t) -> String {
     format!("parsec-se-driver-key{}", key_slot)
 }
 
@@ -167,10 +205,10 @@ fn client_error_to_psa_status(error: Error) -> psa_status_t {
             PSA_ERROR_COMMUNICATION_FAILURE
         }
         Error::Service(ResponseStatus::PsaErrorStorageFailure) => PSA_ERROR_STORAGE_FAILURE,
-        //Error::Service(ResponseStatus::PsaErrorDataCorrupt) => PSA_ERROR_DATA_CORRUPT,
-        //Error::Service(ResponseStatus::PsaErrorDataInvalid) => PSA_ERROR_DATA_INVALID,
+        Error::Service(ResponseStatus::PsaErrorDataCorrupt) => PSA_ERROR_DATA_CORRUPT,
+        Error::Service(ResponseStatus::PsaErrorDataInvalid) => PSA_ERROR_DATA_INVALID,
         Error::Service(ResponseStatus::PsaErrorHardwareFailure) => PSA_ERROR_HARDWARE_FAILURE,
-        //Error::Service(ResponseStatus::PsaErrorCorruptionDetected) => PSA_ERROR_CORRUPTION_DETECTED,
+        Error::Service(ResponseStatus::PsaErrorCorruptionDetected) => PSA_ERROR_CORRUPTION_DETECTED,
         Error::Service(ResponseStatus::PsaErrorInsufficientEntropy) => {
             PSA_ERROR_INSUFFICIENT_ENTROPY
         }
@@ -178,6 +216,20 @@ fn client_error_to_psa_status(error: Error) -> psa_status_t {
         Error::Service(ResponseStatus::PsaErrorInvalidPadding) => PSA_ERROR_INVALID_PADDING,
         Error::Service(ResponseStatus::PsaErrorInsufficientData) => PSA_ERROR_INSUFFICIENT_DATA,
         Error::Service(ResponseStatus::PsaErrorInvalidHandle) => PSA_ERROR_INVALID_HANDLE,
+        Error::Client(kind) => client_error_kind_to_psa_status(kind),
+        e => {
+            error!("Conversion of {:?} to PSA_ERROR_GENERIC_ERROR.", e);
+            PSA_ERROR_GENERIC_ERROR
+        }
+    }
+}
+
+/// Map client/transport-level errors (as opposed to the `ResponseStatus`es the service itself
+/// returns) onto the closest PSA status, so failures to even reach the backing PKCS#11/TPM
+/// provider aren't collapsed into the same generic error as a rejected request.
+fn client_error_kind_to_psa_status(kind: ClientErrorKind) -> psa_status_t {
+    match kind {
+        ClientErrorKind::Io(_) => PSA_ERROR_COMMUNICATION_FAILURE,
         e => {
             error!("Conversion of {:?} to PSA_ERROR_GENERIC_ERROR.", e);
             PSA_ERROR_GENERIC_ERROR
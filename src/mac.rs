@@ -0,0 +1,67 @@
+// Copyright 2020 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+//! One-shot MAC generation and verification forwarded to the Parsec client.
+use crate::{client_error_to_psa_status, key_slot_to_key_name, PARSEC_BASIC_CLIENT};
+use psa_crypto::ffi::{
+    psa_algorithm_t, psa_drv_se_context_t, psa_drv_se_mac_t, psa_key_slot_number_t, psa_status_t,
+    PSA_ERROR_BUFFER_TOO_SMALL, PSA_SUCCESS,
+};
+use std::{ptr, slice};
+
+/// Methods table exposed as `PARSEC_SE_DRIVER.mac`.
+pub static METHODS: psa_drv_se_mac_t = psa_drv_se_mac_t {
+    p_setup: None,
+    p_update: None,
+    p_finish: None,
+    p_finish_verify: None,
+    p_abort: None,
+    p_mac_generate: Some(p_mac_generate),
+    p_mac_verify: Some(p_mac_verify),
+};
+
+unsafe extern "C" fn p_mac_generate(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    alg: psa_algorithm_t,
+    p_input: *const u8,
+    input_length: usize,
+    p_mac: *mut u8,
+    mac_size: usize,
+    p_mac_length: *mut usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let input = slice::from_raw_parts(p_input, input_length);
+
+    match client.psa_mac_compute(key_name, alg, input) {
+        Ok(mac) => {
+            if mac.len() > mac_size {
+                return PSA_ERROR_BUFFER_TOO_SMALL;
+            }
+            ptr::copy_nonoverlapping(mac.as_ptr(), p_mac, mac.len());
+            *p_mac_length = mac.len();
+            PSA_SUCCESS
+        }
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
+
+unsafe extern "C" fn p_mac_verify(
+    _drv_context: *mut psa_drv_se_context_t,
+    key_slot: psa_key_slot_number_t,
+    alg: psa_algorithm_t,
+    p_input: *const u8,
+    input_length: usize,
+    p_mac: *const u8,
+    mac_length: usize,
+) -> psa_status_t {
+    let client = (*PARSEC_BASIC_CLIENT).read().expect("lock poisoned");
+    let key_name = key_slot_to_key_name(key_slot);
+    let input = slice::from_raw_parts(p_input, input_length);
+    let mac = slice::from_raw_parts(p_mac, mac_length);
+
+    match client.psa_mac_verify(key_name, alg, input, mac) {
+        Ok(()) => PSA_SUCCESS,
+        Err(e) => client_error_to_psa_status(e),
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2020 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+//! Serialization of the slot-to-key-name table into the PSA-provided `persistent_data` blob.
+use psa_crypto::ffi::psa_key_slot_number_t;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::os::raw::c_void;
+use std::slice;
+
+/// Maximum number of slots the driver can track.
+pub const MAX_ENTRIES: usize = 32;
+/// Fixed size reserved for a serialized key name (padded with zero bytes).
+const NAME_SIZE: usize = 64;
+/// Size in bytes of one serialized `(slot, name)` entry.
+const ENTRY_SIZE: usize = 8 + NAME_SIZE;
+
+/// Size in bytes of the serialized table; exposed as the driver's `persistent_data_size`.
+pub const TABLE_SIZE: usize = 4 + MAX_ENTRIES * ENTRY_SIZE;
+
+/// Deserialize the slot table out of the PSA-provided `persistent_data` blob.
+///
+/// Returns an empty table when `persistent_data` is null or hasn't been initialised yet
+/// (an entry count of `0`).
+pub unsafe fn read_table(persistent_data: *const c_void) -> HashMap<psa_key_slot_number_t, String> {
+    let mut table = HashMap::new();
+    if persistent_data.is_null() {
+        return table;
+    }
+
+    let bytes = slice::from_raw_parts(persistent_data as *const u8, TABLE_SIZE);
+    let count = u32::from_le_bytes(bytes[0..4].try_into().expect("fixed-size slice")) as usize;
+
+    for i in 0..count.min(MAX_ENTRIES) {
+        let offset = 4 + i * ENTRY_SIZE;
+        let slot = u64::from_le_bytes(
+            bytes[offset..offset + 8]
+                .try_into()
+                .expect("fixed-size slice"),
+        );
+        let name_bytes = &bytes[offset + 8..offset + ENTRY_SIZE];
+        let end = name_bytes.iter().position(|&b| b == 0).unwrap_or(NAME_SIZE);
+        if let Ok(name) = String::from_utf8(name_bytes[..end].to_vec()) {
+            let _ = table.insert(slot, name);
+        }
+    }
+
+    table
+}
+
+/// Serialize the slot table back into the PSA-provided `persistent_data` blob.
+pub unsafe fn write_table(
+    persistent_data: *mut c_void,
+    table: &HashMap<psa_key_slot_number_t, String>,
+) {
+    if persistent_data.is_null() {
+        return;
+    }
+
+    let bytes = slice::from_raw_parts_mut(persistent_data as *mut u8, TABLE_SIZE);
+    for b in bytes.iter_mut() {
+        *b = 0;
+    }
+
+    let count = table.len().min(MAX_ENTRIES) as u32;
+    bytes[0..4].copy_from_slice(&count.to_le_bytes());
+
+    for (i, (slot, name)) in table.iter().take(MAX_ENTRIES).enumerate() {
+        let offset = 4 + i * ENTRY_SIZE;
+        bytes[offset..offset + 8].copy_from_slice(&slot.to_le_bytes());
+        let name_bytes = name.as_bytes();
+        let len = name_bytes.len().min(NAME_SIZE);
+        bytes[offset + 8..offset + 8 + len].copy_from_slice(&name_bytes[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_table, write_table, MAX_ENTRIES, TABLE_SIZE};
+    use std::collections::HashMap;
+    use std::os::raw::c_void;
+
+    #[test]
+    fn round_trips_through_a_buffer() {
+        let mut table = HashMap::new();
+        let _ = table.insert(1, "parsec-se-driver-key1".to_string());
+        let _ = table.insert(2, "parsec-se-driver-key2".to_string());
+
+        let mut buffer = vec![0u8; TABLE_SIZE];
+        let restored = unsafe {
+            write_table(buffer.as_mut_ptr() as *mut c_void, &table);
+            read_table(buffer.as_ptr() as *const c_void)
+        };
+
+        assert_eq!(restored, table);
+    }
+
+    #[test]
+    fn truncates_to_max_entries_on_write() {
+        let table: HashMap<_, _> = (0..MAX_ENTRIES as u64 + 1)
+            .map(|slot| (slot + 1, format!("parsec-se-driver-key{}", slot + 1)))
+            .collect();
+
+        let mut buffer = vec![0u8; TABLE_SIZE];
+        let restored = unsafe {
+            write_table(buffer.as_mut_ptr() as *mut c_void, &table);
+            read_table(buffer.as_ptr() as *const c_void)
+        };
+
+        assert_eq!(restored.len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn read_table_returns_empty_for_null_pointer() {
+        let table = unsafe { read_table(std::ptr::null()) };
+
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright 2020 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+//! Runtime selection of the Parsec provider backing the SE driver.
+use parsec_client::core::interface::operations::list_providers::Uuid;
+use std::env;
+
+/// Environment variable used to pin the backend provider by name, instead of baking the
+/// choice into the binary at compile time via Cargo features.
+const PROVIDER_ENV_VAR: &str = "PARSEC_SE_DRIVER_PROVIDER";
+
+/// UUID of the Core provider, which is never selected automatically.
+const CORE_PROVIDER_UUID: &str = "47049873-2a43-4845-9d72-831eab668784";
+
+/// Known backend provider UUIDs, keyed by the name accepted in `PARSEC_SE_DRIVER_PROVIDER`.
+/// Adding a new backend (e.g. the CryptoAuthLib provider fronting an ATECC508A/608A secure
+/// element) only requires a new entry here, not a new Cargo feature.
+const KNOWN_PROVIDERS: &[(&str, &str)] = &[
+    ("tpm", "1e4954a4-ff21-46d3-ab0c-661eeb667e1d"),
+    ("pkcs11", "30e39502-eba6-4d60-a4af-c518b7f5e38f"),
+    ("cryptoauthlib", "b8ba81e2-e9f7-4bdd-b096-a29d0019960c"),
+];
+
+/// The UUID of the Core provider, exposed so callers can filter it out when no explicit
+/// provider has been configured.
+pub fn core_provider_uuid() -> Uuid {
+    Uuid::parse_str(CORE_PROVIDER_UUID).expect("invalid UUID constant")
+}
+
+/// The outcome of reading `PARSEC_SE_DRIVER_PROVIDER`, distinguishing "unset" (fall back to
+/// "first non-Core provider") from "set to a name we don't recognize" (a misconfiguration that
+/// should fail loudly rather than silently fall back to the same thing).
+pub enum ProviderSelection {
+    /// The variable wasn't set.
+    Unset,
+    /// The variable named a provider we know the UUID for.
+    Known(Uuid),
+    /// The variable was set, but didn't match any entry in `KNOWN_PROVIDERS`.
+    Unknown(String),
+}
+
+/// Read the provider to use from `PARSEC_SE_DRIVER_PROVIDER`.
+pub fn configured_provider() -> ProviderSelection {
+    let name = match env::var(PROVIDER_ENV_VAR) {
+        Ok(name) => name,
+        Err(_) => return ProviderSelection::Unset,
+    };
+
+    match KNOWN_PROVIDERS
+        .iter()
+        .find(|(known_name, _)| known_name.eq_ignore_ascii_case(&name))
+    {
+        Some((_, uuid)) => {
+            ProviderSelection::Known(Uuid::parse_str(uuid).expect("invalid UUID constant"))
+        }
+        None => ProviderSelection::Unknown(name),
+    }
+}